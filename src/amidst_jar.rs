@@ -0,0 +1,153 @@
+//! Resolves and caches the Amidst release matching a world's Minecraft version, so the biome
+//! oracle is always built from a jar whose `RecognisedVersion` actually understands that world's
+//! regions instead of whatever jar happened to be lying around.
+
+use {
+    std::{
+        fs,
+        io,
+        path::{
+            Path,
+            PathBuf
+        }
+    },
+    j4rs::errors::{
+        J4RsError,
+        Result as JResult
+    },
+    sha2::{
+        Digest as _,
+        Sha256
+    }
+};
+
+/// A single Amidst release, together with the inclusive range of Minecraft versions it was built to recognise.
+struct AmidstRelease {
+    min_version: [u16; 3],
+    max_version: [u16; 3],
+    filename: &'static str,
+    download_url: &'static str,
+    /// The release's known-good SHA-256, once a maintainer has verified it against a real download. Until then,
+    /// the cache falls back to trusting (and pinning, via a sidecar file) whatever it downloads the first time.
+    sha256: Option<&'static str>
+}
+
+impl AmidstRelease {
+    fn recognises(&self, version: [u16; 3]) -> bool {
+        version >= self.min_version && version <= self.max_version
+    }
+}
+
+/// Known Amidst releases, keyed by the Minecraft version range they support. See <https://github.com/toolbox4minecraft/amidst/releases>.
+const AMIDST_RELEASES: &[AmidstRelease] = &[
+    AmidstRelease {
+        min_version: [1, 16, 0],
+        max_version: [1, 16, 99],
+        filename: "amidst-v4-6.jar",
+        download_url: "https://github.com/toolbox4minecraft/amidst/releases/download/v4.6/amidst-v4-6.jar",
+        sha256: None //TODO pin once a maintainer has verified the real v4.6 jar's SHA-256
+    },
+    AmidstRelease {
+        min_version: [1, 15, 0],
+        max_version: [1, 15, 99],
+        filename: "amidst-v4-5-beta3.jar",
+        download_url: "https://github.com/toolbox4minecraft/amidst/releases/download/v4.5-beta3/amidst-v4-5-beta3.jar",
+        sha256: None //TODO pin once a maintainer has verified the real v4.5-beta3 jar's SHA-256
+    }
+];
+
+/// Parses a `Data.Version.Name` into a `[major, minor, patch]` triple for range comparisons against
+/// `AmidstRelease`, padding missing components with 0 (e.g. `"1.16"` becomes `[1, 16, 0]`). Snapshot
+/// and other non-numeric version names (e.g. `"20w14a"`) have no well-defined release range, so they
+/// map to `None`, which the caller reports as an unparseable version, rather than erroring out here.
+fn parse_version(name: &str) -> Option<[u16; 3]> {
+    let mut parts = [0u16; 3];
+    let mut found = false;
+    for (part, slot) in name.split('.').zip(parts.iter_mut()) {
+        *slot = part.parse().ok()?;
+        found = true;
+    }
+    found.then(|| parts)
+}
+
+fn jar_cache_dir() -> JResult<PathBuf> {
+    let dir = dirs::cache_dir().ok_or_else(|| J4RsError::GeneralError(format!("no cache directory found for this platform")))?.join("abr").join("amidst-jars");
+    fs::create_dir_all(&dir).map_err(|e| J4RsError::GeneralError(format!("error creating {}: {:?}", dir.display(), e)))?;
+    Ok(dir)
+}
+
+fn sha256_hex(path: &Path) -> JResult<String> {
+    let mut file = fs::File::open(path).map_err(|e| J4RsError::GeneralError(format!("error opening {}: {:?}", path.display(), e)))?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher).map_err(|e| J4RsError::GeneralError(format!("error hashing {}: {:?}", path.display(), e)))?;
+    Ok(hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// Where the checksum recorded at download time is kept, for releases with no maintainer-pinned `sha256`.
+fn sidecar_path(jar_path: &Path) -> PathBuf {
+    let mut path = jar_path.as_os_str().to_owned();
+    path.push(".sha256");
+    PathBuf::from(path)
+}
+
+/// A jar passes verification if it matches the maintainer-pinned checksum (when there is one), or otherwise
+/// matches whatever checksum was recorded next to it the last time it was downloaded (trust on first download).
+fn verify(release: &AmidstRelease, jar_path: &Path) -> JResult<bool> {
+    let actual = sha256_hex(jar_path)?;
+    Ok(match release.sha256 {
+        Some(expected) => actual == expected,
+        None => fs::read_to_string(sidecar_path(jar_path)).map(|recorded| recorded.trim() == actual).unwrap_or(false)
+    })
+}
+
+fn download(release: &AmidstRelease, dest: &Path) -> JResult<()> {
+    eprintln!("downloading {} from {}", release.filename, release.download_url);
+    let bytes = reqwest::blocking::get(release.download_url)
+        .and_then(|res| res.error_for_status())
+        .and_then(|res| res.bytes())
+        .map_err(|e| J4RsError::GeneralError(format!("error downloading {}: {:?}", release.download_url, e)))?;
+    fs::write(dest, &bytes).map_err(|e| J4RsError::GeneralError(format!("error writing {}: {:?}", dest.display(), e)))?;
+    if let Some(expected) = release.sha256 {
+        let actual = sha256_hex(dest)?;
+        if actual != expected {
+            return Err(J4RsError::GeneralError(format!("downloaded {} has SHA-256 {}, expected {}", release.filename, actual, expected)))
+        }
+    } else {
+        let actual = sha256_hex(dest)?;
+        fs::write(sidecar_path(dest), &actual).map_err(|e| J4RsError::GeneralError(format!("error writing checksum sidecar for {}: {:?}", release.filename, e)))?;
+    }
+    Ok(())
+}
+
+/// Returns the path to a cached (downloading it first if necessary) Amidst jar whose `RecognisedVersion` matches the given world.
+///
+/// On every call, whatever jar is already in the cache directory is verified by checksum before being trusted; a jar that's
+/// missing or fails verification is (re-)downloaded from its release on GitHub.
+pub(crate) fn amidst_jar_for_world(world_path: &Path) -> JResult<PathBuf> {
+    let version_name = crate::level_dat::read(world_path)?.version_name;
+    let version = parse_version(&version_name).ok_or_else(|| J4RsError::GeneralError(format!("couldn't parse Minecraft version {:?}", version_name)))?;
+    let release = AMIDST_RELEASES.iter().find(|release| release.recognises(version))
+        .ok_or_else(|| J4RsError::GeneralError(format!("no known Amidst release supports Minecraft {:?}", version)))?;
+    let jar_path = jar_cache_dir()?.join(release.filename);
+    if jar_path.exists() {
+        if verify(release, &jar_path)? {
+            return Ok(jar_path)
+        }
+        eprintln!("cached {} failed checksum verification, redownloading", release.filename);
+    }
+    download(release, &jar_path)?;
+    Ok(jar_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sha256_hex_matches_known_digest() {
+        let path = std::env::temp_dir().join(format!("abr-sha256-test-{}", std::process::id()));
+        fs::write(&path, b"hello world").unwrap();
+        assert_eq!(sha256_hex(&path).unwrap(), "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9");
+        fs::remove_file(&path).unwrap();
+    }
+}
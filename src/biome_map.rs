@@ -0,0 +1,188 @@
+//! Renders a top-down PNG of the scanned area, the way Amidst visualizes worlds: each block column
+//! colored by its biome, with markers at the origin and at each closest target biome, and (if a
+//! railway was planned) the suggested route drawn on top.
+
+use {
+    std::{
+        collections::HashMap,
+        fs,
+        path::{
+            Path,
+            PathBuf
+        }
+    },
+    image::{
+        Rgb,
+        RgbImage
+    },
+    j4rs::errors::{
+        J4RsError,
+        Result as JResult
+    },
+    mcanvil::Biome,
+    serde::Deserialize,
+    crate::{
+        railway::RailwayPlan,
+        region_cache::RegionBiomes
+    }
+};
+
+/// A single horizontal slice through a region's chunks, the shape this module actually draws from. `None`
+/// marks a column whose biome couldn't be determined; `render` leaves those pixels untouched.
+pub(crate) type SurfaceGrid = Box<[[[[Option<Biome>; 16]; 16]; 32]; 32]>;
+
+/// Picks out one vertical section (16 blocks tall) from a fully-resolved region, for use as the map's surface layer.
+pub(crate) fn surface_slice(region_biomes: &RegionBiomes, section: usize) -> SurfaceGrid {
+    let mut buf = Box::<[[_; 32]; 32]>::default();
+    for (cz, chunk_row) in region_biomes.iter().enumerate() {
+        for (cx, chunk) in chunk_row.iter().enumerate() {
+            buf[cz][cx] = chunk[section];
+        }
+    }
+    buf
+}
+
+#[derive(Deserialize, Default)]
+struct RawPalette {
+    #[serde(default)]
+    colors: HashMap<String, [u8; 3]>,
+    default_color: Option<[u8; 3]>
+}
+
+/// Maps biomes to colors for rendering. Biomes with no configured color fall back to `default_color`.
+pub(crate) struct Palette {
+    colors: HashMap<Biome, [u8; 3]>,
+    default_color: [u8; 3]
+}
+
+impl Default for Palette {
+    fn default() -> Palette {
+        let mut colors = HashMap::default();
+        colors.insert(Biome::Ocean, [0, 0, 128]);
+        colors.insert(Biome::DeepOcean, [0, 0, 80]);
+        colors.insert(Biome::Plains, [140, 190, 85]);
+        colors.insert(Biome::Desert, [230, 210, 140]);
+        colors.insert(Biome::Forest, [40, 110, 40]);
+        colors.insert(Biome::Taiga, [40, 90, 75]);
+        colors.insert(Biome::Swamp, [90, 100, 65]);
+        colors.insert(Biome::River, [40, 70, 160]);
+        colors.insert(Biome::Mountains, [120, 120, 120]);
+        colors.insert(Biome::SnowyTundra, [230, 230, 240]);
+        colors.insert(Biome::Jungle, [30, 130, 30]);
+        colors.insert(Biome::Savanna, [180, 170, 90]);
+        colors.insert(Biome::Badlands, [180, 100, 60]);
+        colors.insert(Biome::MushroomFields, [160, 80, 160]);
+        colors.insert(Biome::Beach, [220, 210, 160]);
+        Palette { colors, default_color: [60, 60, 60] }
+    }
+}
+
+impl Palette {
+    /// Loads the built-in defaults, then merges in overrides from `path` if it exists.
+    pub(crate) fn load(path: &Path) -> JResult<Palette> {
+        let mut palette = Palette::default();
+        if path.exists() {
+            let raw: RawPalette = serde_json::from_str(&fs::read_to_string(path).map_err(|e| J4RsError::GeneralError(format!("error reading {}: {:?}", path.display(), e)))?)
+                .map_err(|e| J4RsError::GeneralError(format!("error parsing {}: {:?}", path.display(), e)))?;
+            for (name, color) in raw.colors {
+                let biome = name.parse().map_err(|()| J4RsError::GeneralError(format!("unknown biome in palette: {:?}", name)))?;
+                palette.colors.insert(biome, color);
+            }
+            if let Some(default_color) = raw.default_color {
+                palette.default_color = default_color;
+            }
+        }
+        Ok(palette)
+    }
+
+    fn color(&self, biome: Biome) -> Rgb<u8> {
+        Rgb(*self.colors.get(&biome).unwrap_or(&self.default_color))
+    }
+}
+
+/// The path to the user-editable palette config, alongside the rest of abr's config.
+pub(crate) fn palette_config_path() -> JResult<PathBuf> {
+    Ok(dirs::config_dir().ok_or_else(|| J4RsError::GeneralError(format!("no config directory found for this platform")))?.join("abr").join("biome_palette.json"))
+}
+
+const ORIGIN_COLOR: Rgb<u8> = Rgb([255, 255, 255]);
+const TARGET_COLOR: Rgb<u8> = Rgb([255, 0, 0]);
+const RAIL_COLOR: Rgb<u8> = Rgb([20, 20, 20]);
+
+/// Converts a block coordinate to a pixel coordinate, or `None` if it falls outside the image.
+fn pixel_coord(block: i32, min: i32, scale: u32, bound: u32) -> Option<u32> {
+    let pixel = (block - min) as u32 / scale;
+    if pixel < bound { Some(pixel) } else { None }
+}
+
+fn mark(image: &mut RgbImage, block: [i32; 2], min: [i32; 2], scale: u32, color: Rgb<u8>) {
+    if let (Some(px), Some(pz)) = (pixel_coord(block[0], min[0], scale, image.width()), pixel_coord(block[1], min[1], scale, image.height())) {
+        image.put_pixel(px, pz, color);
+    }
+}
+
+fn draw_leg(image: &mut RgbImage, from: [i32; 2], to: [i32; 2], min: [i32; 2], scale: u32) {
+    if from[0] == to[0] {
+        for z in from[1].min(to[1])..=from[1].max(to[1]) {
+            mark(image, [from[0], z], min, scale, RAIL_COLOR);
+        }
+    } else {
+        for x in from[0].min(to[0])..=from[0].max(to[0]) {
+            mark(image, [x, from[1]], min, scale, RAIL_COLOR);
+        }
+    }
+}
+
+/// Renders the scanned area to `output`, at `scale` blocks per pixel.
+pub(crate) fn render(
+    surface: &HashMap<[i32; 2], SurfaceGrid>,
+    origin: [i32; 2],
+    targets: &HashMap<Biome, [i32; 2]>,
+    railway_plan: Option<&RailwayPlan>,
+    palette: &Palette,
+    scale: u32,
+    output: &Path
+) -> JResult<()> {
+    let mut min = origin;
+    let mut max = origin;
+    for &region_coords in surface.keys() {
+        min[0] = min[0].min(region_coords[0] << 9);
+        min[1] = min[1].min(region_coords[1] << 9);
+        max[0] = max[0].max((region_coords[0] << 9) + 511);
+        max[1] = max[1].max((region_coords[1] << 9) + 511);
+    }
+    let width = ((max[0] - min[0]) as u32 / scale) + 1;
+    let height = ((max[1] - min[1]) as u32 / scale) + 1;
+    let mut image = RgbImage::new(width, height);
+    for (®ion_coords, grid) in surface {
+        for (cz, chunk_row) in grid.iter().enumerate() {
+            for (cx, chunk) in chunk_row.iter().enumerate() {
+                for (bz, block_row) in chunk.iter().enumerate() {
+                    for (bx, &biome) in block_row.iter().enumerate() {
+                        let biome = match biome {
+                            Some(biome) => biome,
+                            None => continue // no determined biome for this column; leave the pixel unpainted
+                        };
+                        let x = (region_coords[0] << 9) + ((cx as i32) << 4) + bx as i32;
+                        let z = (region_coords[1] << 9) + ((cz as i32) << 4) + bz as i32;
+                        if let (Some(px), Some(pz)) = (pixel_coord(x, min[0], scale, width), pixel_coord(z, min[1], scale, height)) {
+                            image.put_pixel(px, pz, palette.color(biome));
+                        }
+                    }
+                }
+            }
+        }
+    }
+    if let Some(railway_plan) = railway_plan {
+        for edge in &railway_plan.edges {
+            for leg in &edge.legs {
+                draw_leg(&mut image, leg.from, leg.to, min, scale);
+            }
+        }
+    }
+    for &coords in targets.values() {
+        mark(&mut image, coords, min, scale, TARGET_COLOR);
+    }
+    mark(&mut image, origin, min, scale, ORIGIN_COLOR);
+    image.save(output).map_err(|e| J4RsError::GeneralError(format!("error writing {}: {:?}", output.display(), e)))
+}
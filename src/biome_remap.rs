@@ -0,0 +1,88 @@
+//! A tolerant fallback layer for biomes that `mcanvil`/Amidst don't recognise, so that a single
+//! unfamiliar numeric ID or namespaced name (from a newer Minecraft version or a datapack) doesn't
+//! abort the whole scan.
+
+use {
+    std::{
+        collections::HashMap,
+        fs,
+        path::{
+            Path,
+            PathBuf
+        }
+    },
+    j4rs::errors::{
+        J4RsError,
+        Result as JResult
+    },
+    mcanvil::Biome,
+    serde::Deserialize
+};
+
+/// What an unrecognised biome ID or name should be treated as.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Remapped {
+    /// Treat every voxel with this ID/name as the given known biome.
+    Biome(Biome),
+    /// Skip the voxel, the same way biomes that haven't been generated yet are skipped.
+    Ignore
+}
+
+fn parse_target(raw: &str) -> JResult<Remapped> {
+    if raw.eq_ignore_ascii_case("ignore") {
+        Ok(Remapped::Ignore)
+    } else {
+        Ok(Remapped::Biome(raw.parse().map_err(|()| J4RsError::GeneralError(format!("unknown biome remap target {:?}", raw)))?))
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct RawConfig {
+    #[serde(default)]
+    by_id: HashMap<i32, String>,
+    #[serde(default)]
+    by_name: HashMap<String, String>
+}
+
+/// A configurable table mapping unknown biome IDs and names onto known `mcanvil::Biome`s (or `Ignore`).
+pub(crate) struct BiomeRemap {
+    by_id: HashMap<i32, Remapped>,
+    by_name: HashMap<String, Remapped>
+}
+
+impl BiomeRemap {
+    fn built_in() -> BiomeRemap {
+        let mut by_id = HashMap::default();
+        by_id.insert(-1, Remapped::Ignore); // some .mca chunks store "not yet generated" as -1 rather than -127
+        BiomeRemap { by_id, by_name: HashMap::default() }
+    }
+
+    /// Loads the built-in defaults, then merges in overrides from `path` if it exists.
+    pub(crate) fn load(path: &Path) -> JResult<BiomeRemap> {
+        let mut remap = BiomeRemap::built_in();
+        if path.exists() {
+            let raw: RawConfig = serde_json::from_str(&fs::read_to_string(path).map_err(|e| J4RsError::GeneralError(format!("error reading {}: {:?}", path.display(), e)))?)
+                .map_err(|e| J4RsError::GeneralError(format!("error parsing {}: {:?}", path.display(), e)))?;
+            for (id, target) in raw.by_id {
+                remap.by_id.insert(id, parse_target(&target)?);
+            }
+            for (name, target) in raw.by_name {
+                remap.by_name.insert(name, parse_target(&target)?);
+            }
+        }
+        Ok(remap)
+    }
+
+    pub(crate) fn resolve_id(&self, id: i32) -> Option<Remapped> {
+        self.by_id.get(&id).copied()
+    }
+
+    pub(crate) fn resolve_name(&self, name: &str) -> Option<Remapped> {
+        self.by_name.get(name).copied()
+    }
+}
+
+/// The path to the user-editable remap config, alongside the rest of abr's config.
+pub(crate) fn config_path() -> JResult<PathBuf> {
+    Ok(dirs::config_dir().ok_or_else(|| J4RsError::GeneralError(format!("no config directory found for this platform")))?.join("abr").join("biome_remap.json"))
+}
@@ -0,0 +1,25 @@
+//! Minimal access to a world's `level.dat`, shared by whatever needs to key behavior off the
+//! world's Minecraft version or seed (the Amidst jar resolver, the region biome cache).
+
+use {
+    std::path::Path,
+    j4rs::errors::{
+        J4RsError,
+        Result as JResult
+    }
+};
+
+pub(crate) struct WorldInfo {
+    /// `Data.Version.Name`, e.g. `"1.16.1"`.
+    pub(crate) version_name: String,
+    /// `Data.WorldGenSettings.seed`.
+    pub(crate) seed: i64
+}
+
+pub(crate) fn read(world_path: &Path) -> JResult<WorldInfo> {
+    let level_dat = mcanvil::LevelDat::open(world_path.join("level.dat")).map_err(|e| J4RsError::GeneralError(format!("error reading level.dat: {:?}", e)))?;
+    Ok(WorldInfo {
+        version_name: level_dat.data.version.name,
+        seed: level_dat.data.world_gen_settings.seed
+    })
+}
@@ -7,6 +7,8 @@ use {
             TryFrom as _,
             TryInto as _
         },
+        env,
+        fs,
         io,
         path::{
             Path,
@@ -30,6 +32,25 @@ use {
     rayon::prelude::*
 };
 
+mod amidst_jar;
+mod biome_map;
+mod biome_remap;
+mod level_dat;
+mod railway;
+mod region_cache;
+
+use self::{
+    biome_map::SurfaceGrid,
+    biome_remap::{
+        BiomeRemap,
+        Remapped
+    },
+    region_cache::{
+        RegionBiomes,
+        RegionCache
+    }
+};
+
 const ADV_TIME_BIOMES: [Biome; 42] = [
     Biome::Badlands,
     Biome::BadlandsPlateau,
@@ -75,19 +96,68 @@ const ADV_TIME_BIOMES: [Biome; 42] = [
     Biome::WoodedMountains
 ];
 
+/// Number of 16×16 biome sections stacked vertically in a chunk, one per 16 blocks of world height.
+const NUM_SECTIONS: usize = 16;
+
+/// The result of scanning outwards for the closest occurrence of every `ADV_TIME_BIOMES` entry: the coordinates found, plus
+/// the 16-block section containing the origin's Y of every region touched along the way, kept around so a biome map can be rendered without rescanning.
+struct ScanResult {
+    found: HashMap<Biome, [i32; 3]>,
+    surface: HashMap<[i32; 2], SurfaceGrid>
+}
+
+/// A region that either already came back from the cache, or still needs its ungenerated chunks seeded via the JVM oracle.
+enum RegionEntry {
+    Cached(RegionBiomes),
+    Raw(Box<[[Option<[[[Option<Biome>; 16]; 16]; NUM_SECTIONS]>; 32]; 32]>)
+}
+
 struct World {
     amidst: Mutex<Instance>,
-    region_path: PathBuf
+    region_path: PathBuf,
+    biome_remap: BiomeRemap,
+    region_cache: RegionCache
 }
 
 impl World {
     fn open(jvm: &Jvm, path: &Path) -> JResult<World> {
+        let world_info = level_dat::read(path)?;
+        let remap_config_path = biome_remap::config_path()?;
         Ok(World {
             amidst: Mutex::new(load_amidst_world(&jvm, path.to_str().ok_or_else(|| J4RsError::GeneralError(format!("path is not valid UTF-8")))?)?),
-            region_path: path.join("region")
+            region_path: path.join("region"),
+            biome_remap: BiomeRemap::load(&remap_config_path)?,
+            region_cache: RegionCache::for_world(&world_info.version_name, world_info.seed, &remap_config_path)?
         })
     }
 
+    /// Looks up a region in the on-disk cache without touching the JVM, falling back to reading the region file (with ungenerated chunks not yet seeded) on a miss.
+    fn region_entry(&self, coords: [i32; 2]) -> JResult<RegionEntry> {
+        let mca_mtime = fs::metadata(self.region_path.join(format!("r.{}.{}.mca", coords[0], coords[1]))).and_then(|metadata| metadata.modified()).ok();
+        if let Some(cached) = self.region_cache.get(coords, mca_mtime) {
+            return Ok(RegionEntry::Cached(cached))
+        }
+        Ok(RegionEntry::Raw(self.region_biomes(coords)?))
+    }
+
+    /// Resolves a region entry into its fully-resolved biome grid, paying the JVM oracle cost (and writing back to the cache) only on a miss.
+    fn resolve_region(&self, jvm: &Jvm, coords: [i32; 2], entry: RegionEntry) -> JResult<RegionBiomes> {
+        match entry {
+            RegionEntry::Cached(biomes) => Ok(biomes),
+            RegionEntry::Raw(raw) => {
+                let resolved = self.biomes_for_region(jvm, coords, raw)?;
+                self.region_cache.put(coords, &resolved)?;
+                Ok(resolved)
+            }
+        }
+    }
+
+    /// Returns the fully-resolved biome grid for a region, consulting the on-disk cache before paying the decode and oracle cost.
+    fn region(&self, jvm: &Jvm, coords: [i32; 2]) -> JResult<RegionBiomes> {
+        let entry = self.region_entry(coords)?;
+        self.resolve_region(jvm, coords, entry)
+    }
+
     fn region_uncached(&self, [region_x, region_z]: [i32; 2]) -> JResult<Option<mcanvil::Region>> {
         match mcanvil::Region::open(self.region_path.join(format!("r.{}.{}.mca", region_x, region_z))) {
             Ok(region) => Ok(Some(region)),
@@ -96,25 +166,44 @@ impl World {
         }
     }
 
-    /// Returns the biome that would be found at the given block coordinates if the chunk that block column is in were to be generated now.
-    fn seed_biome(&self, jvm: &Jvm, [x, z]: [i32; 2]) -> JResult<Biome> {
+    /// Returns the biome that would be found at the given block column if the chunk it's in were to be generated now, or `None`
+    /// if it maps to a `Remapped::Ignore` entry and should be skipped rather than recorded as a real biome occurrence. Amidst's
+    /// `BiomeDataOracle` is 2D (it has no concept of height), so the result is the same for every section in the column.
+    fn seed_biome(&self, jvm: &Jvm, [x, z]: [i32; 2]) -> JResult<Option<Biome>> {
         let amidst_biome = jvm.invoke(
             &jvm.invoke(&self.amidst.lock(), "getBiomeDataOracle", &[])?,
             "getBiomeAt",
             &[InvocationArg::try_from(x)?.into_primitive()?, InvocationArg::try_from(z)?.into_primitive()?, InvocationArg::try_from(false)?.into_primitive()?]
         )?;
-        Ok(jvm.to_rust::<String>(jvm.invoke(&amidst_biome, "getName", &[])?)?.parse().map_err(|()| J4RsError::GeneralError(format!("unknown biome name")))?)
+        let name = jvm.to_rust::<String>(jvm.invoke(&amidst_biome, "getName", &[])?)?;
+        match name.parse() {
+            Ok(biome) => Ok(Some(biome)),
+            Err(()) => match self.biome_remap.resolve_name(&name) {
+                Some(Remapped::Biome(biome)) => Ok(Some(biome)),
+                Some(Remapped::Ignore) => Ok(None),
+                None => Err(J4RsError::GeneralError(format!("unknown biome name {:?}", name)))
+            }
+        }
     }
 
-    fn region_biomes(&self, coords: [i32; 2]) -> JResult<Box<[[Option<[[Biome; 16]; 16]>; 32]; 32]>> {
+    /// Returns the full vertical stack of biome sections for every chunk column in the region, leaving columns that aren't generated yet as `None`.
+    fn region_biomes(&self, coords: [i32; 2]) -> JResult<Box<[[Option<[[[Option<Biome>; 16]; 16]; NUM_SECTIONS]>; 32]; 32]>> {
         let mut buf = Box::<[[_; 32]; 32]>::default();
         if let Some(region) = self.region_uncached(coords)? {
             for chunk_col in &region {
                 let chunk_col = chunk_col.map_err(|e| J4RsError::GeneralError(format!("error decoding chunk column in region {:?}: {:?}", coords, e)))?;
                 let biomes_for_chunk = match chunk_col.biomes() {
-                    Ok([biomes, ..]) => biomes,
+                    // mcanvil stores biomes per 4×4×4 voxel, so the number of vertical sections it hands back isn't
+                    // guaranteed at compile time to match NUM_SECTIONS; check rather than assume.
+                    Ok(sections) => <[[[Biome; 16]; 16]; NUM_SECTIONS]>::try_from(sections)
+                        .map_err(|sections: Vec<_>| J4RsError::GeneralError(format!("chunk {}/{} has {} biome sections, expected {}", chunk_col.level.x_pos, chunk_col.level.z_pos, sections.len(), NUM_SECTIONS)))?
+                        .map(|section| section.map(|row| row.map(Some))),
                     Err(Some(-127)) => continue, // invalid biome, regenerate
-                    Err(Some(bid)) => return Err(J4RsError::GeneralError(format!("unknown biome ID {} in chunk {}/{}", bid, chunk_col.level.x_pos, chunk_col.level.z_pos))),
+                    Err(Some(bid)) => match self.biome_remap.resolve_id(bid) {
+                        Some(Remapped::Biome(biome)) => [[[Some(biome); 16]; 16]; NUM_SECTIONS],
+                        Some(Remapped::Ignore) => continue,
+                        None => return Err(J4RsError::GeneralError(format!("unknown biome ID {} in chunk {}/{}", bid, chunk_col.level.x_pos, chunk_col.level.z_pos)))
+                    },
                     Err(None) => continue // biomes not yet generated for this chunk column
                 };
                 buf[chunk_col.level.z_pos as usize % 32][chunk_col.level.x_pos as usize % 32] = Some(biomes_for_chunk);
@@ -123,16 +212,20 @@ impl World {
         Ok(buf)
     }
 
-    fn biomes_for_region(&self, jvm: &Jvm, [rx, rz]: [i32; 2], region_biomes: Box<[[Option<[[Biome; 16]; 16]>; 32]; 32]>) -> JResult<Box<[[[[Biome; 16]; 16]; 32]; 32]>> {
+    fn biomes_for_region(&self, jvm: &Jvm, [rx, rz]: [i32; 2], region_biomes: Box<[[Option<[[[Option<Biome>; 16]; 16]; NUM_SECTIONS]>; 32]; 32]>) -> JResult<RegionBiomes> {
         let mut buf = Box::<[[_; 32]; 32]>::default();
         for (cz, chunk_row) in region_biomes.iter().enumerate() {
             for (cx, opt_chunk) in chunk_row.iter().enumerate() {
                 if let Some(chunk) = opt_chunk {
                     buf[cz][cx] = *chunk;
                 } else {
+                    // the oracle has no height axis, so seed each column once and replicate it across every section
                     for bz in 0..16 {
                         for bx in 0..16 {
-                            buf[cz][cx][bz as usize][bx as usize] = self.seed_biome(jvm, [(rz << 9) + ((cz as i32) << 4) + bx, (rx << 9) + ((cx as i32) << 4) + bz])?;
+                            let biome = self.seed_biome(jvm, [(rx << 9) + ((cx as i32) << 4) + bx, (rz << 9) + ((cz as i32) << 4) + bz])?;
+                            for section in 0..NUM_SECTIONS {
+                                buf[cz][cx][section][bz as usize][bx as usize] = biome;
+                            }
                         }
                     }
                 }
@@ -141,20 +234,26 @@ impl World {
         Ok(buf)
     }
 
-    fn closest_adv_time_biomes(&self, jvm: &Jvm, coords: [i32; 2]) -> JResult<HashMap<Biome, [i32; 2]>> {
-        let region_coords = [coords[0] >> 9, coords[1] >> 9];
-        let mut found = self.closest_biomes_in_region(jvm, coords, region_coords, self.region_biomes(region_coords)?)?.into_iter().filter(|(biome, _)| ADV_TIME_BIOMES.contains(biome)).collect::<HashMap<_, _>>();
+    fn closest_adv_time_biomes(&self, jvm: &Jvm, coords: [i32; 3]) -> JResult<ScanResult> {
+        let region_coords = [coords[0] >> 9, coords[2] >> 9];
+        let section = ((coords[1].max(0) as usize) / 16).min(NUM_SECTIONS - 1);
+        let mut surface = HashMap::default();
+        let initial_region_biomes = self.region(jvm, region_coords)?;
+        surface.insert(region_coords, biome_map::surface_slice(&initial_region_biomes, section));
+        let mut found = self.closest_biomes_in_region(coords, region_coords, initial_region_biomes)?.into_iter().filter(|(biome, _)| ADV_TIME_BIOMES.contains(biome)).collect::<HashMap<_, _>>();
         let mut all_found = 0;
         let mut regions_scanned = 1;
         let mut total_regions = None;
         for distance in 1.. {
-            let partial_biomes = coords_at_distance(region_coords, distance)
-                .map(|reg| Ok((reg, self.region_biomes(reg)?)))
+            let partial_entries = coords_at_distance(region_coords, distance)
+                .map(|reg| Ok((reg, self.region_entry(reg)?)))
                 .collect::<JResult<Vec<_>>>()?;
-            for (reg, region_biomes) in partial_biomes {
-                for (biome, [x, z]) in self.closest_biomes_in_region(jvm, coords, reg, region_biomes)? {
-                    if ADV_TIME_BIOMES.contains(&biome) && taxicab_distance(coords, [x, z]) < taxicab_distance(coords, *found.entry(biome).or_insert([x, z])) {
-                        found.insert(biome, [x, z]);
+            for (reg, entry) in partial_entries {
+                let region_biomes = self.resolve_region(jvm, reg, entry)?;
+                surface.insert(reg, biome_map::surface_slice(&region_biomes, section));
+                for (biome, [x, y, z]) in self.closest_biomes_in_region(coords, reg, region_biomes)? {
+                    if ADV_TIME_BIOMES.contains(&biome) && taxicab_distance(coords, [x, y, z]) < taxicab_distance(coords, *found.entry(biome).or_insert([x, y, z])) {
+                        found.insert(biome, [x, y, z]);
                     }
                 }
                 regions_scanned += 1;
@@ -175,22 +274,28 @@ impl World {
             }
         }
         eprintln!();
-        for (biome, &[x, z]) in found.iter().sorted_by_key(|(biome, &[x, z])| (taxicab_distance(coords, [x, z]), z, x, biome.to_string())) {
-            let biome_dist = taxicab_distance(coords, [x, z]);
-            eprintln!("closest {} at {}/{} (distance: {}m)", biome, x, z, biome_dist);
+        for (biome, &[x, y, z]) in found.iter().sorted_by_key(|(biome, &[x, y, z])| (taxicab_distance(coords, [x, y, z]), z, x, biome.to_string())) {
+            let biome_dist = taxicab_distance(coords, [x, y, z]);
+            eprintln!("closest {} at {}/{}/{} (distance: {}m)", biome, x, y, z, biome_dist);
         }
-        Ok(found)
+        Ok(ScanResult { found, surface })
     }
 
-    fn closest_biomes_in_region(&self, jvm: &Jvm, coords: [i32; 2], [rx, rz]: [i32; 2], region_biomes: Box<[[Option<[[Biome; 16]; 16]>; 32]; 32]>) -> JResult<HashMap<Biome, [i32; 2]>> {
+    fn closest_biomes_in_region(&self, coords: [i32; 3], [rx, rz]: [i32; 2], region_biomes: RegionBiomes) -> JResult<HashMap<Biome, [i32; 3]>> {
         let mut found = HashMap::default();
-        for (cz, chunk_row) in self.biomes_for_region(jvm, [rx, rz], region_biomes)?.iter().enumerate() {
+        for (cz, chunk_row) in region_biomes.iter().enumerate() {
             for (cx, chunk) in chunk_row.iter().enumerate() {
-                for (bz, block_row) in chunk.iter().enumerate() {
-                    for (bx, &biome) in block_row.iter().enumerate() {
-                        let block_coords = [(rx << 9) + ((cx as i32) << 4) + bx as i32, (rz << 9) + ((cz as i32) << 4) + bz as i32];
-                        if taxicab_distance(coords, block_coords) < taxicab_distance(coords, *found.entry(biome).or_insert(block_coords)) {
-                            found.insert(biome, block_coords);
+                for (section, section_biomes) in chunk.iter().enumerate() {
+                    for (bz, block_row) in section_biomes.iter().enumerate() {
+                        for (bx, &biome) in block_row.iter().enumerate() {
+                            let biome = match biome {
+                                Some(biome) => biome,
+                                None => continue // oracle name remapped to Remapped::Ignore; not a real biome occurrence
+                            };
+                            let block_coords = [(rx << 9) + ((cx as i32) << 4) + bx as i32, (section as i32) << 4, (rz << 9) + ((cz as i32) << 4) + bz as i32];
+                            if taxicab_distance(coords, block_coords) < taxicab_distance(coords, *found.entry(biome).or_insert(block_coords)) {
+                                found.insert(biome, block_coords);
+                            }
                         }
                     }
                 }
@@ -316,14 +421,42 @@ fn coords_at_distance([x, z]: [i32; 2], distance: i32) -> impl ParallelIterator<
         .chain((0..distance).into_par_iter().map(move |d| [x - distance + d, z - d]))
 }
 
-fn taxicab_distance([x1, z1]: [i32; 2], [x2, z2]: [i32; 2]) -> u32 {
-    (x2 - x1).abs() as u32 + (z2 - z1).abs() as u32
+/// Generalizes over both the horizontal (block `[x, z]`, as used by the railway planner) and full 3D (block `[x, y, z]`) coordinate spaces.
+pub(crate) fn taxicab_distance<const N: usize>(a: [i32; N], b: [i32; N]) -> u32 {
+    a.iter().zip(&b).map(|(x1, x2)| (x2 - x1).abs() as u32).sum()
+}
+
+/// Parses `--map-output <path>` (default `biome_map.png`) and `--scale <blocks-per-pixel>` (default `4`) from the command line.
+fn parse_map_args() -> JResult<(PathBuf, u32)> {
+    let mut map_output = PathBuf::from("biome_map.png");
+    let mut scale = 4;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match &arg[..] {
+            "--map-output" => map_output = PathBuf::from(args.next().ok_or_else(|| J4RsError::GeneralError(format!("--map-output needs a value")))?),
+            "--scale" => scale = args.next().ok_or_else(|| J4RsError::GeneralError(format!("--scale needs a value")))?.parse().map_err(|e| J4RsError::GeneralError(format!("invalid --scale: {:?}", e)))?,
+            _ => return Err(J4RsError::GeneralError(format!("unknown argument: {}", arg)))
+        }
+    }
+    Ok((map_output, scale))
 }
 
 fn main() -> JResult<()> {
+    let (map_output, scale) = parse_map_args()?;
     let path = Path::new("C:\\Users\\Fenhl\\games\\minecraft\\srv\\wmb\\backup\\wmb-world_2020-07-03_21-57-12_1.16.1"); //TODO add command-line option to change the path?
-    let jvm = JvmBuilder::new().classpath_entry(ClasspathEntry::new("C:\\Users\\Fenhl\\games\\minecraft\\amidst-v4-5-beta3.jar")).build()?; //TODO auto-download appropriate Amidst release?
+    let amidst_jar = amidst_jar::amidst_jar_for_world(path)?;
+    let jvm = JvmBuilder::new().classpath_entry(ClasspathEntry::new(amidst_jar.to_str().ok_or_else(|| J4RsError::GeneralError(format!("amidst jar path is not valid UTF-8")))?)).build()?;
     let world = World::open(&jvm, path)?;
-    let _ = world.closest_adv_time_biomes(&jvm, [3386, 3096])?; //TODO suggest a path for the railway
+    let origin = [3386, 64, 3096]; //TODO allow specifying Y on the command line
+    let scan = world.closest_adv_time_biomes(&jvm, origin)?;
+    let targets = scan.found.into_iter().map(|(biome, [x, _, z])| (biome, [x, z])).collect();
+    let railway_plan = railway::plan([origin[0], origin[2]], &targets);
+    eprintln!("suggested railway: {}m of track across {} edges", railway_plan.total_length, railway_plan.edges.len());
+    for edge in &railway_plan.edges {
+        eprintln!("  {}m via {:?}", edge.length, edge.waypoints);
+    }
+    let palette = biome_map::Palette::load(&biome_map::palette_config_path()?)?;
+    biome_map::render(&scan.surface, [origin[0], origin[2]], &targets, Some(&railway_plan), &palette, scale, &map_output)?;
+    eprintln!("wrote biome map to {}", map_output.display());
     Ok(())
 }
@@ -0,0 +1,76 @@
+//! Suggests a minimal rail network connecting the origin to every located target biome.
+
+use {
+    std::collections::HashMap,
+    mcanvil::Biome,
+    crate::taxicab_distance
+};
+
+/// One straight, axis-aligned leg of track between two waypoints.
+pub(crate) struct RailLeg {
+    pub(crate) from: [i32; 2],
+    pub(crate) to: [i32; 2],
+    pub(crate) length: u32
+}
+
+/// One edge of the minimum spanning tree, realized as a straight or L-shaped rail path.
+pub(crate) struct RailEdge {
+    pub(crate) waypoints: Vec<[i32; 2]>,
+    pub(crate) legs: Vec<RailLeg>,
+    pub(crate) length: u32
+}
+
+pub(crate) struct RailwayPlan {
+    pub(crate) edges: Vec<RailEdge>,
+    pub(crate) total_length: u32
+}
+
+/// Builds a minimum spanning tree (Prim's, O(V²), fine for the ~42 `ADV_TIME_BIOMES` plus origin) over the
+/// origin and every target biome coordinate, then realizes each tree edge as an axis-aligned rail path: since
+/// Minecraft rail only runs orthogonally, diagonal edges become an L-shaped path (X then Z).
+pub(crate) fn plan(origin: [i32; 2], targets: &HashMap<Biome, [i32; 2]>) -> RailwayPlan {
+    let mut nodes = vec![origin];
+    for &coords in targets.values() {
+        if !nodes.contains(&coords) {
+            nodes.push(coords);
+        }
+    }
+    let len = nodes.len();
+    let mut in_tree = vec![false; len];
+    let mut best_dist = vec![u32::MAX; len];
+    let mut best_from = vec![0; len];
+    in_tree[0] = true;
+    for i in 1..len {
+        best_dist[i] = taxicab_distance(nodes[0], nodes[i]);
+        best_from[i] = 0;
+    }
+    let mut tree_edges = Vec::with_capacity(len.saturating_sub(1));
+    for _ in 1..len {
+        let next = (0..len).filter(|&i| !in_tree[i]).min_by_key(|&i| best_dist[i]).expect("graph is connected since every node has a finite taxicab distance to every other");
+        in_tree[next] = true;
+        tree_edges.push((best_from[next], next));
+        for i in 0..len {
+            if !in_tree[i] {
+                let dist = taxicab_distance(nodes[next], nodes[i]);
+                if dist < best_dist[i] {
+                    best_dist[i] = dist;
+                    best_from[i] = next;
+                }
+            }
+        }
+    }
+    let edges = tree_edges.into_iter().map(|(a, b)| rail_edge(nodes[a], nodes[b])).collect::<Vec<_>>();
+    let total_length = edges.iter().map(|edge| edge.length).sum();
+    RailwayPlan { edges, total_length }
+}
+
+fn rail_edge(from: [i32; 2], to: [i32; 2]) -> RailEdge {
+    let waypoints = if from[0] == to[0] || from[1] == to[1] {
+        vec![from, to]
+    } else {
+        vec![from, [to[0], from[1]], to] // move fully along X, then fully along Z
+    };
+    let legs = waypoints.windows(2).map(|pair| RailLeg { from: pair[0], to: pair[1], length: taxicab_distance(pair[0], pair[1]) }).collect::<Vec<_>>();
+    let length = legs.iter().map(|leg| leg.length).sum();
+    RailEdge { waypoints, legs, length }
+}
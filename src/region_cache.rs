@@ -0,0 +1,104 @@
+//! A persistent on-disk cache of fully-resolved region biome grids (decoded `.mca` sections plus
+//! any oracle-seeded chunks), so repeated runs against the same world don't pay the decode and
+//! JVM oracle cost again for regions that haven't changed.
+
+use {
+    std::{
+        fs,
+        path::{
+            Path,
+            PathBuf
+        },
+        time::SystemTime
+    },
+    j4rs::errors::{
+        J4RsError,
+        Result as JResult
+    },
+    mcanvil::Biome,
+    sha2::{
+        Digest as _,
+        Sha256
+    },
+    crate::NUM_SECTIONS
+};
+
+/// `None` means the column's biome couldn't be determined (e.g. an unknown name remapped to `Remapped::Ignore`),
+/// and should be skipped rather than treated as a real biome occurrence.
+pub(crate) type RegionBiomes = Box<[[[[[Option<Biome>; 16]; 16]; NUM_SECTIONS]; 32]; 32]>;
+
+/// Bump this whenever the on-disk shape of a cached grid changes, so stale entries in that shape are ignored
+/// rather than misparsed.
+const CACHE_FORMAT_VERSION: u32 = 1;
+
+/// `mcanvil::Biome` doesn't derive `Serialize`/`Deserialize` (this crate goes through `FromStr`/`Display` for it
+/// everywhere else, e.g. `biome_remap`/`biome_map`), so a cached grid is stored as nested biome name strings
+/// rather than bincoding `Biome` directly.
+type DiskGrid = Vec<Vec<Vec<Vec<Vec<Option<String>>>>>>;
+
+fn to_disk(biomes: &RegionBiomes) -> DiskGrid {
+    biomes.iter().map(|chunk_row| chunk_row.iter().map(|chunk| chunk.iter()
+        .map(|section| section.iter().map(|row| row.iter().map(|biome| biome.map(|biome| biome.to_string())).collect()).collect())
+        .collect()).collect()).collect()
+}
+
+fn from_disk(disk: DiskGrid) -> JResult<RegionBiomes> {
+    let mut buf = Box::<[[_; 32]; 32]>::default();
+    for (cz, chunk_row) in disk.into_iter().enumerate() {
+        for (cx, chunk) in chunk_row.into_iter().enumerate() {
+            for (section, section_grid) in chunk.into_iter().enumerate() {
+                for (bz, row) in section_grid.into_iter().enumerate() {
+                    for (bx, name) in row.into_iter().enumerate() {
+                        buf[cz][cx][section][bz][bx] = name.map(|name| name.parse().map_err(|()| J4RsError::GeneralError(format!("unknown biome name in region cache: {:?}", name)))).transpose()?;
+                    }
+                }
+            }
+        }
+    }
+    Ok(buf)
+}
+
+/// A short fingerprint of the remap config, so edits to it invalidate grids resolved under the old config instead
+/// of silently returning stale remapped biomes. Worlds with no config file all share the same (built-in-only) fingerprint.
+fn remap_fingerprint(remap_config_path: &Path) -> String {
+    let contents = fs::read(remap_config_path).unwrap_or_default();
+    Sha256::digest(&contents).iter().take(8).map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Caches resolved region grids under a directory keyed by the world's version and seed plus the cache format
+/// and remap config fingerprint, so grids from different worlds, different generations of the same world, a
+/// changed remap config, or an older on-disk shape never collide with or shadow each other.
+pub(crate) struct RegionCache {
+    dir: PathBuf
+}
+
+impl RegionCache {
+    pub(crate) fn for_world(version_name: &str, seed: i64, remap_config_path: &Path) -> JResult<RegionCache> {
+        let dir = dirs::cache_dir().ok_or_else(|| J4RsError::GeneralError(format!("no cache directory found for this platform")))?
+            .join("abr").join("region-cache").join(format!("{}_{}_v{}_{}", version_name, seed, CACHE_FORMAT_VERSION, remap_fingerprint(remap_config_path)));
+        fs::create_dir_all(&dir).map_err(|e| J4RsError::GeneralError(format!("error creating {}: {:?}", dir.display(), e)))?;
+        Ok(RegionCache { dir })
+    }
+
+    fn path(&self, [rx, rz]: [i32; 2]) -> PathBuf {
+        self.dir.join(format!("r.{}.{}.bin", rx, rz))
+    }
+
+    /// Returns the cached grid for this region, unless it's missing or older than `mca_mtime`.
+    pub(crate) fn get(&self, coords: [i32; 2], mca_mtime: Option<SystemTime>) -> Option<RegionBiomes> {
+        let path = self.path(coords);
+        let cache_mtime = fs::metadata(&path).and_then(|metadata| metadata.modified()).ok()?;
+        if let Some(mca_mtime) = mca_mtime {
+            if mca_mtime > cache_mtime {
+                return None
+            }
+        }
+        from_disk(bincode::deserialize(&fs::read(&path).ok()?).ok()?).ok()
+    }
+
+    pub(crate) fn put(&self, coords: [i32; 2], biomes: &RegionBiomes) -> JResult<()> {
+        let path = self.path(coords);
+        let bytes = bincode::serialize(&to_disk(biomes)).map_err(|e| J4RsError::GeneralError(format!("error serializing region cache entry {}: {:?}", path.display(), e)))?;
+        fs::write(&path, bytes).map_err(|e| J4RsError::GeneralError(format!("error writing {}: {:?}", path.display(), e)))
+    }
+}